@@ -1,7 +1,53 @@
-use bevy::prelude::*;
+use std::collections::HashMap;
 
+use bevy::{input::gamepad::GamepadButton, prelude::*};
+
+// The `serde` feature (see the `config` module below) requires bevy's own `serialize` feature,
+// which is what gives `KeyCode`/`GamepadAxisType`/`GamepadButtonType` their Serialize/Deserialize impls.
+
+/// Tracks connected gamepads by slot, so multiple [`crate::DebugCamera`] entities can each be
+/// driven by a distinct controller. A new pad takes the lowest free slot, not always slot zero.
 #[derive(Resource, Default, Debug)]
-pub struct ActiveGamepad(pub Option<Gamepad>);
+pub struct GamepadRegistry {
+    slots: Vec<Option<Gamepad>>,
+}
+
+impl GamepadRegistry {
+    /// Assigns `gamepad` to the lowest free slot, growing the registry if every existing slot is
+    /// taken. Returns the slot it landed in.
+    pub fn connect(&mut self, gamepad: Gamepad) -> usize {
+        if let Some(slot) = self.slots.iter().position(Option::is_none) {
+            self.slots[slot] = Some(gamepad);
+            slot
+        } else {
+            self.slots.push(Some(gamepad));
+            self.slots.len() - 1
+        }
+    }
+
+    /// Frees the slot held by `gamepad`, if any. Returns the slot that was freed.
+    pub fn disconnect(&mut self, gamepad: Gamepad) -> Option<usize> {
+        let slot = self.slots.iter().position(|g| *g == Some(gamepad))?;
+        self.slots[slot] = None;
+        Some(slot)
+    }
+
+    /// The lowest-slot connected gamepad.
+    pub fn first(&self) -> Option<Gamepad> {
+        self.slots.iter().flatten().next().copied()
+    }
+
+    /// Every connected gamepad, lowest slot first.
+    pub fn connected(&self) -> impl Iterator<Item = Gamepad> + '_ {
+        self.slots.iter().flatten().copied()
+    }
+}
+
+/// Assigns a specific connected gamepad to a [`crate::DebugCamera`]. Cameras without this
+/// component fall back to the lowest-slot connected gamepad that no camera's `AssignedGamepad`
+/// already claims.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct AssignedGamepad(pub Gamepad);
 
 /// This system signals whether the debug camera should be active. You can selectively pick which
 /// input types are active at a given time. You can
@@ -32,6 +78,8 @@ impl Default for DebugCameraActive {
 /// Configurable bindings for keyboard input. Field defaults can be found in the crate root
 /// documentation.
 #[derive(Resource, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct KeyboardBindings {
     pub fwd: KeyCode,
     pub bwd: KeyCode,
@@ -59,15 +107,24 @@ impl Default for KeyboardBindings {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GamepadInput {
     Axis(GamepadAxisType),
     Button(GamepadButtonType),
     Trigger(GamepadButtonType),
+    /// Treats `axis` as digital: past `threshold` it contributes a full `dir.signum()` instead of
+    /// the analog value `Axis` would.
+    AxisThreshold {
+        axis: GamepadAxisType,
+        threshold: f32,
+    },
 }
 
 /// Configurable bindings for gamepad input. Field defaults can be found in the crate root
 /// documentation.
 #[derive(Resource, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct GamepadBindings {
     pub fwd: GamepadInput,
     pub bwd: GamepadInput,
@@ -101,3 +158,446 @@ impl Default for GamepadBindings {
         }
     }
 }
+
+/// A logical camera control, independent of any specific key, gamepad input, or mouse axis.
+/// [`InputMap`] binds each of these to the physical inputs that drive it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CameraAction {
+    Forward,
+    Back,
+    Up,
+    Down,
+    Left,
+    Right,
+    YawLeft,
+    YawRight,
+    PitchUp,
+    PitchDown,
+    RollLeft,
+    RollRight,
+}
+
+/// One axis of mouse motion, usable as a bindable input alongside keys and gamepad inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MouseAxis {
+    X,
+    Y,
+}
+
+/// A single physical input that can be bound to a [`CameraAction`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PhysicalInput {
+    Key(KeyCode),
+    Gamepad(GamepadInput),
+    Mouse(MouseAxis),
+}
+
+/// Configurable mapping from logical [`CameraAction`]s to the physical inputs that drive them.
+/// Unlike [`KeyboardBindings`]/[`GamepadBindings`], each action may have any number of bound
+/// inputs; `camera_movement_system` sums their contributions. Defaults to today's bindings, with
+/// mouse motion driving look.
+#[derive(Resource, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputMap {
+    bindings: HashMap<CameraAction, Vec<PhysicalInput>>,
+}
+
+impl InputMap {
+    /// The physical inputs currently bound to `action`, in bind order.
+    pub fn bindings(&self, action: CameraAction) -> &[PhysicalInput] {
+        self.bindings
+            .get(&action)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Binds an additional physical input to `action`, alongside any existing bindings.
+    pub fn bind(&mut self, action: CameraAction, input: PhysicalInput) {
+        self.bindings.entry(action).or_default().push(input);
+    }
+
+    /// Removes every binding currently on `action`.
+    pub fn clear(&mut self, action: CameraAction) {
+        self.bindings.remove(&action);
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> InputMap {
+        let keyboard = KeyboardBindings::default();
+        let gamepad = GamepadBindings::default();
+        let mut bindings: HashMap<CameraAction, Vec<PhysicalInput>> = HashMap::new();
+
+        let mut bind = |action, inputs: Vec<PhysicalInput>| {
+            bindings.insert(action, inputs);
+        };
+        bind(
+            CameraAction::Forward,
+            vec![PhysicalInput::Key(keyboard.fwd), PhysicalInput::Gamepad(gamepad.fwd)],
+        );
+        bind(
+            CameraAction::Back,
+            vec![PhysicalInput::Key(keyboard.bwd), PhysicalInput::Gamepad(gamepad.bwd)],
+        );
+        bind(
+            CameraAction::Up,
+            vec![PhysicalInput::Key(keyboard.up), PhysicalInput::Gamepad(gamepad.up)],
+        );
+        bind(
+            CameraAction::Down,
+            vec![PhysicalInput::Key(keyboard.down), PhysicalInput::Gamepad(gamepad.down)],
+        );
+        bind(
+            CameraAction::Left,
+            vec![PhysicalInput::Key(keyboard.left), PhysicalInput::Gamepad(gamepad.left)],
+        );
+        bind(
+            CameraAction::Right,
+            vec![PhysicalInput::Key(keyboard.right), PhysicalInput::Gamepad(gamepad.right)],
+        );
+        bind(
+            CameraAction::YawLeft,
+            vec![PhysicalInput::Gamepad(gamepad.yaw_left), PhysicalInput::Mouse(MouseAxis::X)],
+        );
+        bind(
+            CameraAction::YawRight,
+            vec![PhysicalInput::Gamepad(gamepad.yaw_right), PhysicalInput::Mouse(MouseAxis::X)],
+        );
+        bind(
+            CameraAction::PitchUp,
+            vec![PhysicalInput::Gamepad(gamepad.pitch_up), PhysicalInput::Mouse(MouseAxis::Y)],
+        );
+        bind(
+            CameraAction::PitchDown,
+            vec![PhysicalInput::Gamepad(gamepad.pitch_down), PhysicalInput::Mouse(MouseAxis::Y)],
+        );
+        bind(
+            CameraAction::RollLeft,
+            vec![PhysicalInput::Key(keyboard.roll_left), PhysicalInput::Gamepad(gamepad.roll_left)],
+        );
+        bind(
+            CameraAction::RollRight,
+            vec![PhysicalInput::Key(keyboard.roll_right), PhysicalInput::Gamepad(gamepad.roll_right)],
+        );
+
+        InputMap { bindings }
+    }
+}
+
+/// Magnitude an analog stick axis must cross during rebind capture to count as "activated".
+pub const REBIND_AXIS_THRESHOLD: f32 = 0.5;
+
+/// One physical input activated during an in-progress [`RebindRequest`] capture. Unlike
+/// [`PhysicalInput`], an axis entry also remembers which direction it was pushed in, so
+/// `RebindRequest::capture` can tell a single stick nudge apart from two opposing ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CapturedInput {
+    Key(KeyCode),
+    GamepadButton(GamepadButtonType),
+    GamepadTrigger(GamepadButtonType),
+    GamepadAxis { axis: GamepadAxisType, sign: f32 },
+}
+
+impl CapturedInput {
+    /// Converts a settled capture into the [`PhysicalInput`] it should bind to an action.
+    pub fn into_physical_input(self) -> PhysicalInput {
+        match self {
+            CapturedInput::Key(key) => PhysicalInput::Key(key),
+            CapturedInput::GamepadButton(button) => PhysicalInput::Gamepad(GamepadInput::Button(button)),
+            CapturedInput::GamepadTrigger(button) => PhysicalInput::Gamepad(GamepadInput::Trigger(button)),
+            CapturedInput::GamepadAxis { axis, .. } => PhysicalInput::Gamepad(GamepadInput::Axis(axis)),
+        }
+    }
+}
+
+/// An in-progress request to capture the next physical input(s) pressed and bind them to
+/// `action` in [`InputMap`]. Insert to start listening; `rebind_capture_system` fills in
+/// `captured` frame by frame (expose it for a "press a key..." prompt) and removes the resource
+/// once every capture is released.
+#[derive(Resource, Debug, Clone)]
+pub struct RebindRequest {
+    pub action: CameraAction,
+    pub captured: Vec<CapturedInput>,
+}
+
+impl RebindRequest {
+    pub fn new(action: CameraAction) -> RebindRequest {
+        RebindRequest {
+            action,
+            captured: Vec::new(),
+        }
+    }
+
+    /// Records `input` as activated this frame, evicting any previously-captured opposite
+    /// direction of the same axis.
+    pub fn capture(&mut self, input: CapturedInput) {
+        if self.captured.contains(&input) {
+            return;
+        }
+        if let CapturedInput::GamepadAxis { axis, sign } = input {
+            self.captured.retain(|existing| {
+                !matches!(
+                    existing,
+                    CapturedInput::GamepadAxis { axis: a, sign: s } if *a == axis && *s == -sign
+                )
+            });
+        }
+        self.captured.push(input);
+    }
+
+    /// True once every captured input has been released (or, for an axis, has fallen back under
+    /// [`REBIND_AXIS_THRESHOLD`]), meaning the user is done pressing and the capture can commit.
+    pub fn is_settled(
+        &self,
+        keys: &Input<KeyCode>,
+        buttons: &Input<GamepadButton>,
+        axes: &Axis<GamepadAxis>,
+        gamepad: Option<Gamepad>,
+    ) -> bool {
+        self.captured.iter().all(|captured| match captured {
+            CapturedInput::Key(key) => !keys.pressed(*key),
+            CapturedInput::GamepadButton(button) | CapturedInput::GamepadTrigger(button) => gamepad
+                .map_or(true, |gamepad| {
+                    !buttons.pressed(GamepadButton::new(gamepad, *button))
+                }),
+            CapturedInput::GamepadAxis { axis, .. } => gamepad.map_or(true, |gamepad| {
+                axes.get(GamepadAxis::new(gamepad, *axis))
+                    .map_or(true, |v| v.abs() <= REBIND_AXIS_THRESHOLD)
+            }),
+        })
+    }
+}
+
+/// Configurable input response for look (yaw/pitch) controls. `input_value` consults this
+/// instead of relying on `GamepadSettings` defaults.
+#[derive(Resource, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DebugCameraSensitivity {
+    /// Multiplier applied to mouse look input.
+    pub mouse_look: f32,
+    /// Multiplier applied to gamepad stick look input.
+    pub gamepad_look: f32,
+    /// Raw stick axis values in `[lower, upper]` are treated as zero.
+    pub stick_deadzone_lower: f32,
+    pub stick_deadzone_upper: f32,
+    /// When set, `RightStickX`/`RightStickY` are treated as a single 2D vector, zeroed and
+    /// rescaled by magnitude instead of per-axis.
+    pub radial_deadzone: Option<f32>,
+    /// Exponential smoothing factor applied to the accumulated look rotation, frame to frame.
+    /// `0.0` is instant (no smoothing); closer to `1.0` is a heavier low-pass filter.
+    pub look_smoothing: f32,
+}
+
+impl Default for DebugCameraSensitivity {
+    fn default() -> DebugCameraSensitivity {
+        DebugCameraSensitivity {
+            mouse_look: 1.0,
+            gamepad_look: 1.0,
+            stick_deadzone_lower: -0.1,
+            stick_deadzone_upper: 0.1,
+            radial_deadzone: None,
+            look_smoothing: 0.0,
+        }
+    }
+}
+
+impl DebugCameraSensitivity {
+    /// Applies the configured linear deadzone to a single raw stick axis value.
+    pub fn apply_deadzone(&self, value: f32) -> f32 {
+        if (self.stick_deadzone_lower..=self.stick_deadzone_upper).contains(&value) {
+            0.0
+        } else {
+            value
+        }
+    }
+
+    /// Applies the optional radial deadzone to a stick pair (see [`Self::radial_deadzone`]).
+    /// Returns `value` unchanged when no radial deadzone is configured.
+    pub fn apply_radial_deadzone(&self, value: Vec2) -> Vec2 {
+        let Some(threshold) = self.radial_deadzone else {
+            return value;
+        };
+        let magnitude = value.length();
+        if magnitude <= threshold || threshold >= 1.0 {
+            return Vec2::ZERO;
+        }
+        value.normalize() * ((magnitude - threshold) / (1.0 - threshold)).min(1.0)
+    }
+}
+
+/// Tracks the exponentially-smoothed look rotation per camera entity, carried frame to frame so
+/// [`DebugCameraSensitivity::look_smoothing`] can low-pass filter mouse and stick look input.
+#[derive(Resource, Debug, Default)]
+pub struct LookSmoothingState {
+    smoothed: HashMap<Entity, Vec3>,
+}
+
+impl LookSmoothingState {
+    /// Blends `raw` into the rotation tracked for `entity` by `smoothing` (see
+    /// [`DebugCameraSensitivity::look_smoothing`]) and returns the updated, smoothed value.
+    pub fn smooth(&mut self, entity: Entity, raw: Vec3, smoothing: f32) -> Vec3 {
+        let previous = self.smoothed.get(&entity).copied().unwrap_or(Vec3::ZERO);
+        let smoothed = previous.lerp(raw, 1.0 - smoothing.clamp(0.0, 0.999));
+        self.smoothed.insert(entity, smoothed);
+        smoothed
+    }
+
+    /// Drops any tracked state for `entity`, so a despawned (or `DebugCamera`-less) camera
+    /// doesn't linger in the map forever.
+    pub fn remove(&mut self, entity: Entity) {
+        self.smoothed.remove(&entity);
+    }
+}
+
+/// Loading and saving a user's customized controls. Gated behind the `serde` feature.
+#[cfg(feature = "serde")]
+pub mod config {
+    use std::io;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::{DebugCameraSensitivity, InputMap};
+
+    /// Everything a user might want to persist across runs. Missing fields fall back to
+    /// [`Default`].
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct DebugCameraConfig {
+        pub input_map: InputMap,
+        pub sensitivity: DebugCameraSensitivity,
+    }
+
+    /// Reads a [`DebugCameraConfig`] from `reader`.
+    pub fn load_config<R: io::Read>(reader: R) -> serde_json::Result<DebugCameraConfig> {
+        serde_json::from_reader(reader)
+    }
+
+    /// Writes `config` to `writer` in the same format [`load_config`] reads.
+    pub fn save_config<W: io::Write>(writer: W, config: &DebugCameraConfig) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(writer, config)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{super::CameraAction, *};
+
+        #[test]
+        fn round_trips() {
+            let config = DebugCameraConfig::default();
+            let mut bytes = Vec::new();
+            save_config(&mut bytes, &config).expect("save_config");
+
+            let loaded = load_config(bytes.as_slice()).expect("load_config");
+            assert_eq!(loaded.sensitivity.mouse_look, config.sensitivity.mouse_look);
+            assert_eq!(
+                loaded.input_map.bindings(CameraAction::Forward).len(),
+                config.input_map.bindings(CameraAction::Forward).len()
+            );
+        }
+
+        #[test]
+        fn missing_fields_fall_back_to_default() {
+            let loaded: DebugCameraConfig = serde_json::from_str("{}").expect("load_config");
+            assert_eq!(
+                loaded.sensitivity.mouse_look,
+                DebugCameraSensitivity::default().mouse_look
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_replaces_opposite_axis_direction() {
+        let mut request = RebindRequest::new(CameraAction::YawLeft);
+        request.capture(CapturedInput::GamepadAxis {
+            axis: GamepadAxisType::RightStickX,
+            sign: -1.0,
+        });
+        request.capture(CapturedInput::GamepadAxis {
+            axis: GamepadAxisType::RightStickX,
+            sign: 1.0,
+        });
+
+        assert_eq!(
+            request.captured,
+            vec![CapturedInput::GamepadAxis {
+                axis: GamepadAxisType::RightStickX,
+                sign: 1.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn capture_keeps_distinct_axes() {
+        let mut request = RebindRequest::new(CameraAction::Forward);
+        request.capture(CapturedInput::GamepadAxis {
+            axis: GamepadAxisType::LeftStickY,
+            sign: 1.0,
+        });
+        request.capture(CapturedInput::GamepadAxis {
+            axis: GamepadAxisType::RightStickX,
+            sign: -1.0,
+        });
+
+        assert_eq!(request.captured.len(), 2);
+    }
+
+    #[test]
+    fn registry_assigns_lowest_free_slot() {
+        let mut registry = GamepadRegistry::default();
+        let a = registry.connect(Gamepad::new(0));
+        let b = registry.connect(Gamepad::new(1));
+        assert_eq!((a, b), (0, 1));
+
+        registry.disconnect(Gamepad::new(0));
+        let c = registry.connect(Gamepad::new(2));
+        assert_eq!(c, 0);
+    }
+
+    #[test]
+    fn registry_disconnect_is_idempotent() {
+        let mut registry = GamepadRegistry::default();
+        registry.connect(Gamepad::new(0));
+        assert_eq!(registry.disconnect(Gamepad::new(0)), Some(0));
+        assert_eq!(registry.disconnect(Gamepad::new(0)), None);
+        assert_eq!(registry.first(), None);
+    }
+
+    #[test]
+    fn radial_deadzone_zeroes_within_threshold() {
+        let sensitivity = DebugCameraSensitivity {
+            radial_deadzone: Some(0.2),
+            ..Default::default()
+        };
+        assert_eq!(
+            sensitivity.apply_radial_deadzone(Vec2::new(0.1, 0.1)),
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn radial_deadzone_rescales_beyond_threshold() {
+        let sensitivity = DebugCameraSensitivity {
+            radial_deadzone: Some(0.5),
+            ..Default::default()
+        };
+        let result = sensitivity.apply_radial_deadzone(Vec2::new(0.75, 0.0));
+        assert!((result.x - 0.5).abs() < 1e-6);
+        assert_eq!(result.y, 0.0);
+    }
+
+    #[test]
+    fn radial_deadzone_noop_when_unconfigured() {
+        let sensitivity = DebugCameraSensitivity::default();
+        let value = Vec2::new(0.05, -0.05);
+        assert_eq!(sensitivity.apply_radial_deadzone(value), value);
+    }
+}
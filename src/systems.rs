@@ -1,12 +1,16 @@
-use std::ops::Neg;
+use std::{collections::HashSet, ops::Neg};
 
 use crate::{
     components::DebugCamera,
-    resources::{ActiveGamepad, DebugCameraActive, GamepadBindings, KeyboardBindings, GamepadInput},
+    resources::{
+        AssignedGamepad, CapturedInput, DebugCameraActive, DebugCameraSensitivity, GamepadInput,
+        GamepadRegistry, InputMap, LookSmoothingState, MouseAxis, PhysicalInput, RebindRequest,
+        CameraAction, REBIND_AXIS_THRESHOLD,
+    },
 };
 use bevy::{
     input::{
-        gamepad::{GamepadButton, GamepadSettings, GamepadEvent, GamepadConnection},
+        gamepad::{GamepadButton, GamepadEvent, GamepadConnection},
         mouse::MouseMotion,
     },
     prelude::*,
@@ -19,17 +23,18 @@ use bevy::{
 /// explicitly does *not* update the camera's tranform.
 #[allow(clippy::too_many_arguments)]
 pub fn camera_movement_system(
-    mut q: Query<&mut DebugCamera>,
+    mut q: Query<(Entity, &mut DebugCamera, Option<&AssignedGamepad>)>,
     time: Res<Time>,
     keys: Res<Input<KeyCode>>,
     debug_camera_active: Res<DebugCameraActive>,
-    keyboard_bindings: Res<KeyboardBindings>,
-    gamepad_bindings: Res<GamepadBindings>,
+    input_map: Res<InputMap>,
+    sensitivity: Res<DebugCameraSensitivity>,
+    mut look_smoothing: ResMut<LookSmoothingState>,
     mut motion_evr: EventReader<MouseMotion>,
     axes: Res<Axis<GamepadAxis>>,
     buttons: Res<Input<GamepadButton>>,
     button_axes: Res<Axis<GamepadButton>>,
-    active_gamepad: ResMut<ActiveGamepad>,
+    gamepad_registry: Res<GamepadRegistry>,
 ) {
     // Shortcut if neither control scheme is active. This is not strictly needed, but it avoids
     // some computation if controls are inactive.
@@ -37,71 +42,72 @@ pub fn camera_movement_system(
         return;
     }
 
-    // All calculations before going into each camera are done from the camera's frame
-    // of reference. We assume x = fwd, y = right, z = up
-    let mut rotate_vec = Vec3::default();
-    let mut local_translate_vec = Vec3::default();
-
-    // First, apply controller if present and active
-    if debug_camera_active.gamepad {
-        if let Some(gamepad) = active_gamepad.0 {
-            // Apply translation
-            let left = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.left, -1.0);
-            let right = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.right, 1.0);
-            let fwd = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.fwd, 1.0);
-            let bwd = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.bwd, -1.0);
-            let up = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.up, 1.0);
-            let down = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.down, -1.0);
-            local_translate_vec += time.delta_seconds() * Vec3::new(fwd + bwd, up + down, left + right);
-
-            // Apply rotation
-            let yaw_left = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.yaw_left, -1.0);
-            let yaw_right = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.yaw_right, 1.0);
-            let pitch_up = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.pitch_up, 1.0);
-            let pitch_down = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.pitch_down, -1.0);
-            let roll_left = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.roll_left, -1.0);
-            let roll_right = input_value(gamepad, &axes, &buttons, &button_axes, &gamepad_bindings.roll_right, 1.0);
-            rotate_vec += time.delta_seconds() * Vec3::new(-yaw_left - yaw_right, pitch_up + pitch_down, roll_left + roll_right);
+    let mouse_delta = {
+        let mut d = Vec2::default();
+        for ev in motion_evr.iter() {
+            d -= ev.delta;
         }
-    }
+        d
+    };
+    let mut frame = FrameInputs {
+        active: &debug_camera_active,
+        sensitivity: &sensitivity,
+        keys: &keys,
+        mouse_delta,
+        gamepad: None,
+        axes: &axes,
+        buttons: &buttons,
+        button_axes: &button_axes,
+    };
 
-    // Next, apply keyboard and mouse controls
-    if debug_camera_active.keymouse {
-        let key_fwd = keys.pressed(keyboard_bindings.fwd);
-        let key_bwd = keys.pressed(keyboard_bindings.bwd);
-        let key_up = keys.pressed(keyboard_bindings.up);
-        let key_down = keys.pressed(keyboard_bindings.down);
-        let key_left = keys.pressed(keyboard_bindings.left);
-        let key_right = keys.pressed(keyboard_bindings.right);
-        let key_roll_left = keys.pressed(keyboard_bindings.roll_left);
-        let key_roll_right = keys.pressed(keyboard_bindings.roll_right);
-        let mouse_delta = {
-            let mut d = Vec2::default();
-            for ev in motion_evr.iter() {
-                d -= ev.delta;
-            }
-            d
-        };
+    // Pads already claimed by some camera's `AssignedGamepad`, so unassigned cameras don't also
+    // fall back onto them.
+    let claimed_gamepads: HashSet<Gamepad> = q
+        .iter()
+        .filter_map(|(_, _, assigned)| assigned.map(|assigned| assigned.0))
+        .collect();
 
-        // All keyboard and mouse input is multiplied by 0.5, as otherwise it will go too fast
-        // compared with controller
-        local_translate_vec += time.delta_seconds()
-            * 0.5
-            * Vec3::new(
-                buttons_to_dir(key_fwd, key_bwd),
-                buttons_to_dir(key_up, key_down),
-                buttons_to_dir(key_right, key_left),
-            );
-        rotate_vec += time.delta_seconds()
-            * 0.5
+    for (entity, mut controlled_camera, assigned_gamepad) in q.iter_mut() {
+        // Each camera may be driven by its own controller, falling back to the lowest-slot
+        // connected pad not already claimed by another camera when it has no explicit assignment.
+        frame.gamepad = assigned_gamepad.map(|assigned| assigned.0).or_else(|| {
+            gamepad_registry
+                .connected()
+                .find(|pad| !claimed_gamepads.contains(pad))
+        });
+
+        // All calculations here are done from the camera's frame of reference. We assume x =
+        // fwd, y = right, z = up
+        let fwd = action_value(CameraAction::Forward, 1.0, false, &input_map, &frame);
+        let bwd = action_value(CameraAction::Back, -1.0, false, &input_map, &frame);
+        let up = action_value(CameraAction::Up, 1.0, false, &input_map, &frame);
+        let down = action_value(CameraAction::Down, -1.0, false, &input_map, &frame);
+        let left = action_value(CameraAction::Left, -1.0, false, &input_map, &frame);
+        let right = action_value(CameraAction::Right, 1.0, false, &input_map, &frame);
+        let local_translate_vec =
+            time.delta_seconds() * Vec3::new(fwd + bwd, up + down, left + right);
+
+        let yaw_left = action_value(CameraAction::YawLeft, -1.0, true, &input_map, &frame);
+        let yaw_right = action_value(CameraAction::YawRight, 1.0, true, &input_map, &frame);
+        let pitch_up = action_value(CameraAction::PitchUp, 1.0, true, &input_map, &frame);
+        let pitch_down = action_value(CameraAction::PitchDown, -1.0, true, &input_map, &frame);
+        let roll_left = action_value(CameraAction::RollLeft, -1.0, false, &input_map, &frame);
+        let roll_right = action_value(CameraAction::RollRight, 1.0, false, &input_map, &frame);
+        let raw_rotate_vec = time.delta_seconds()
             * Vec3::new(
-                mouse_delta.x,
-                mouse_delta.y,
-                buttons_to_dir(key_roll_right, key_roll_left),
+                -yaw_left - yaw_right,
+                pitch_up + pitch_down,
+                roll_left + roll_right,
             );
-    }
+        // Only look (yaw/pitch) is smoothed; roll is driven by discrete key/button presses, so
+        // smoothing it would just add input lag.
+        let smoothed_look = look_smoothing.smooth(
+            entity,
+            raw_rotate_vec.with_z(0.0),
+            sensitivity.look_smoothing,
+        );
+        let rotate_vec = smoothed_look.with_z(raw_rotate_vec.z);
 
-    for mut controlled_camera in q.iter_mut() {
         // We start by computing and correcting all our basis vectors to be unit vectors that are
         // perpendicular to each other. This fixes any
         let mut right = controlled_camera.fwd.cross(controlled_camera.up);
@@ -148,6 +154,18 @@ pub fn camera_movement_system(
     }
 }
 
+/// Prunes [`LookSmoothingState`] entries for cameras that despawned or dropped their
+/// [`DebugCamera`] component, so the map doesn't grow unbounded in an app that spawns and
+/// destroys debug cameras repeatedly (e.g. an editor toggling views).
+pub fn prune_look_smoothing_system(
+    mut removed: RemovedComponents<DebugCamera>,
+    mut look_smoothing: ResMut<LookSmoothingState>,
+) {
+    for entity in removed.iter() {
+        look_smoothing.remove(entity);
+    }
+}
+
 /// This system is responsible for updating the camera's transform according to the [`DebugCamera`]
 /// component. When both control methods are off, this system stops updating, letting you control
 /// the camera independently (though we recommend removing the component entirely if you want to
@@ -183,69 +201,239 @@ pub fn cursor_grab_system(
     }
 }
 
-/// This system manages gamepad connections and sets the current active gamepad. It will set the
-/// [`ActiveGamepad`] resource to said gamepad ID, and will send a tracing event on set and unset.
+/// This system maintains the [`GamepadRegistry`]: it assigns each connecting gamepad to the
+/// lowest free slot and frees a pad's slot when it disconnects, rather than letting a new pad
+/// always grab slot zero. It sends a tracing event for every slot assignment or release.
 pub fn gamepad_connections(
-    mut active_gamepad: ResMut<ActiveGamepad>,
+    mut gamepad_registry: ResMut<GamepadRegistry>,
     mut gamepad_evr: EventReader<GamepadEvent>,
-    mut settings: ResMut<GamepadSettings>,
 ) {
     for ev in gamepad_evr.iter() {
         // Only matching again
-        match &ev {
-            GamepadEvent::Connection(event_info) => {
-                // the ID of the gamepad
-                let id = event_info.gamepad.id;
-                match &event_info.connection {
-                    GamepadConnection::Connected(info) => {
-                        if active_gamepad.0.is_none() {
-                            event!(
-                                Level::INFO,
-                                event = "active_gamepad_set",
-                                gamepad_name = info.name,
-                                gamepad_id = id,
-                            );
-                            active_gamepad.0 = Some(event_info.gamepad);
-        
-                            // Configure controller for better use
-                            settings.default_axis_settings.set_deadzone_lowerbound(-0.1);
-                            settings.default_axis_settings.set_deadzone_upperbound(0.1);
-                        }
-                    }
-                    GamepadConnection::Disconnected => {
-                        let mut remove_gamepad = false;
-                        if let Some(old_id) = active_gamepad.0 {
-                            if old_id == event_info.gamepad {
-                                event!(
-                                    Level::INFO,
-                                    event = "active_gamepad_removed",
-                                    gamepad_id = id,
-                                );
-                                remove_gamepad = true;
-                            }
-                        }
-                        if remove_gamepad {
-                            active_gamepad.0 = None;
-                        }
-                    }
+        let GamepadEvent::Connection(event_info) = ev else {
+            continue;
+        };
+        // the ID of the gamepad
+        let id = event_info.gamepad.id;
+        match &event_info.connection {
+            GamepadConnection::Connected(info) => {
+                let slot = gamepad_registry.connect(event_info.gamepad);
+                event!(
+                    Level::INFO,
+                    event = "gamepad_slot_assigned",
+                    gamepad_name = info.name,
+                    gamepad_id = id,
+                    slot,
+                );
+            }
+            GamepadConnection::Disconnected => {
+                if let Some(slot) = gamepad_registry.disconnect(event_info.gamepad) {
+                    event!(
+                        Level::INFO,
+                        event = "gamepad_slot_freed",
+                        gamepad_id = id,
+                        slot,
+                    );
                 }
             }
-            _ => ()
         }
     }
 }
 
+/// Drives an in-progress [`RebindRequest`]: each frame it scans for freshly-pressed keys,
+/// gamepad buttons, and gamepad axes crossing [`REBIND_AXIS_THRESHOLD`], accumulating them onto
+/// the request. Only the lowest-slot connected gamepad ([`GamepadRegistry::first`]) is
+/// considered, regardless of any camera's `AssignedGamepad`. Once every captured input has been
+/// released, the capture is committed to `input_map` and the [`RebindRequest`] resource is
+/// removed, so a caller starts a rebind simply by inserting the resource and polls `captured` in
+/// the meantime to render a "press a key..." prompt.
+pub fn rebind_capture_system(
+    mut commands: Commands,
+    request: Option<ResMut<RebindRequest>>,
+    mut input_map: ResMut<InputMap>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepad_registry: Res<GamepadRegistry>,
+) {
+    let Some(mut request) = request else {
+        return;
+    };
+    let active_gamepad = gamepad_registry.first();
+
+    for key in keys.get_just_pressed() {
+        request.capture(CapturedInput::Key(*key));
+    }
+
+    if let Some(gamepad) = active_gamepad {
+        for button in buttons.get_just_pressed() {
+            if button.gamepad != gamepad {
+                continue;
+            }
+            if is_trigger_button(button.button_type) {
+                request.capture(CapturedInput::GamepadTrigger(button.button_type));
+            } else {
+                request.capture(CapturedInput::GamepadButton(button.button_type));
+            }
+        }
+        for gamepad_axis in axes.devices() {
+            if gamepad_axis.gamepad != gamepad {
+                continue;
+            }
+            if let Some(v) = axes.get(*gamepad_axis) {
+                if v.abs() > REBIND_AXIS_THRESHOLD {
+                    request.capture(CapturedInput::GamepadAxis {
+                        axis: gamepad_axis.axis_type,
+                        sign: v.signum(),
+                    });
+                }
+            }
+        }
+    }
+
+    if request.captured.is_empty() || !request.is_settled(&keys, &buttons, &axes, active_gamepad) {
+        return;
+    }
+
+    input_map.clear(request.action);
+    for input in request.captured.drain(..) {
+        input_map.bind(request.action, input.into_physical_input());
+    }
+    commands.remove_resource::<RebindRequest>();
+}
+
+/// The gamepad buttons this crate treats as analog triggers (bound via `GamepadInput::Trigger`)
+/// rather than plain on/off buttons, matching `GamepadBindings::default`'s `up`/`down` bindings.
+fn is_trigger_button(button: GamepadButtonType) -> bool {
+    matches!(
+        button,
+        GamepadButtonType::LeftTrigger2 | GamepadButtonType::RightTrigger2
+    )
+}
+
+/// Bundles the physical-input resources `camera_movement_system` needs to resolve
+/// [`CameraAction`] values.
+struct FrameInputs<'a> {
+    active: &'a DebugCameraActive,
+    sensitivity: &'a DebugCameraSensitivity,
+    keys: &'a Input<KeyCode>,
+    mouse_delta: Vec2,
+    gamepad: Option<Gamepad>,
+    axes: &'a Axis<GamepadAxis>,
+    buttons: &'a Input<GamepadButton>,
+    button_axes: &'a Axis<GamepadButton>,
+}
+
+/// Resolves the net value of `action` this frame by summing every physical input bound to it in
+/// `input_map`. `dir` is the signed direction this action represents (e.g. `Forward` is `1.0`,
+/// `Back` is `-1.0`). `is_look` additionally scales mouse/gamepad contributions by
+/// [`DebugCameraSensitivity::mouse_look`]/[`gamepad_look`](DebugCameraSensitivity::gamepad_look).
+/// Translate actions are clamped to `[-1, 1]` so redundant bindings (e.g. WASD and arrow keys on
+/// the same action) don't stack past a single full-strength input; look actions are left
+/// unclamped so `mouse_look`/`gamepad_look` can still scale a turn faster than `1.0`.
+fn action_value(
+    action: CameraAction,
+    dir: f32,
+    is_look: bool,
+    input_map: &InputMap,
+    frame: &FrameInputs,
+) -> f32 {
+    let net: f32 = input_map
+        .bindings(action)
+        .iter()
+        .map(|input| match input {
+            PhysicalInput::Key(key) => {
+                if frame.active.keymouse && frame.keys.pressed(*key) {
+                    0.5 * dir.signum()
+                } else {
+                    0.0
+                }
+            }
+            PhysicalInput::Mouse(axis) => {
+                if !frame.active.keymouse {
+                    return 0.0;
+                }
+                // Yaw is assembled as `-(yaw_left + yaw_right)` below (matching the sign
+                // convention `input_value` already uses for the yaw stick axis), while pitch is
+                // assembled as a plain sum, so the X axis needs a compensating flip here to land
+                // on the same rotation direction as before this was unified with the stick.
+                let v = match axis {
+                    MouseAxis::X => -frame.mouse_delta.x,
+                    MouseAxis::Y => frame.mouse_delta.y,
+                };
+                if v.signum() == dir.signum() {
+                    let look_sensitivity = if is_look { frame.sensitivity.mouse_look } else { 1.0 };
+                    0.5 * look_sensitivity * v
+                } else {
+                    0.0
+                }
+            }
+            PhysicalInput::Gamepad(input) => {
+                if !frame.active.gamepad {
+                    return 0.0;
+                }
+                match frame.gamepad {
+                    Some(gamepad) => {
+                        let look_sensitivity = if is_look { frame.sensitivity.gamepad_look } else { 1.0 };
+                        look_sensitivity
+                            * input_value(
+                                gamepad,
+                                frame.axes,
+                                frame.buttons,
+                                frame.button_axes,
+                                frame.sensitivity,
+                                input,
+                                dir,
+                            )
+                    }
+                    None => 0.0,
+                }
+            }
+        })
+        .sum();
+
+    if is_look {
+        net
+    } else {
+        net.clamp(-1.0, 1.0)
+    }
+}
+
 fn input_value(
     gamepad: Gamepad,
     axes: &Axis<GamepadAxis>,
     buttons: &Input<GamepadButton>,
     button_axes: &Axis<GamepadButton>,
+    sensitivity: &DebugCameraSensitivity,
     input: &GamepadInput,
     dir: f32,
 ) -> f32 {
     match input {
         GamepadInput::Axis(axis) => {
-            if let Some(v) = axes.get(GamepadAxis::new(gamepad, *axis)) {
+            let raw = |a: GamepadAxisType| axes.get(GamepadAxis::new(gamepad, a));
+            // RightStickX/Y are treated as a look stick pair so an optional radial deadzone can
+            // be applied across both at once; every other axis just gets the linear deadzone.
+            let v = match axis {
+                GamepadAxisType::RightStickX | GamepadAxisType::RightStickY => {
+                    let pair = Vec2::new(
+                        raw(GamepadAxisType::RightStickX).unwrap_or(0.0),
+                        raw(GamepadAxisType::RightStickY).unwrap_or(0.0),
+                    );
+                    let adjusted = match sensitivity.radial_deadzone {
+                        Some(_) => sensitivity.apply_radial_deadzone(pair),
+                        None => Vec2::new(
+                            sensitivity.apply_deadzone(pair.x),
+                            sensitivity.apply_deadzone(pair.y),
+                        ),
+                    };
+                    Some(match axis {
+                        GamepadAxisType::RightStickX => adjusted.x,
+                        _ => adjusted.y,
+                    })
+                }
+                _ => raw(*axis).map(|v| sensitivity.apply_deadzone(v)),
+            };
+            if let Some(v) = v {
                 if v.signum() == dir.signum() {
                     return v;
                 }
@@ -261,16 +449,17 @@ fn input_value(
                 return v * dir.signum();
             }
         }
+        GamepadInput::AxisThreshold { axis, threshold } => {
+            if let Some(v) = axes.get(GamepadAxis::new(gamepad, *axis)) {
+                if v.signum() == dir.signum() && v.abs() > *threshold {
+                    return dir.signum();
+                }
+            }
+        }
     }
     return 0.0;
 }
 
-fn buttons_to_dir<T>(positive: bool, negative: bool) -> T
-where T: From<i8>
-{
-    (Into::<i8>::into(positive) - Into::<i8>::into(negative)).into()
-}
-
 #[inline(always)]
 fn invert<T>(v: T, invert: bool) -> T
 where T: Neg<Output = T>
@@ -280,3 +469,44 @@ where T: Neg<Output = T>
         false => v,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axis_threshold_value(raw: f32, threshold: f32, dir: f32) -> f32 {
+        let gamepad = Gamepad::new(0);
+        let mut axes = Axis::<GamepadAxis>::default();
+        axes.set(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX), raw);
+        let buttons = Input::<GamepadButton>::default();
+        let button_axes = Axis::<GamepadButton>::default();
+        let sensitivity = DebugCameraSensitivity::default();
+        input_value(
+            gamepad,
+            &axes,
+            &buttons,
+            &button_axes,
+            &sensitivity,
+            &GamepadInput::AxisThreshold {
+                axis: GamepadAxisType::LeftStickX,
+                threshold,
+            },
+            dir,
+        )
+    }
+
+    #[test]
+    fn axis_threshold_triggers_past_threshold_in_dir() {
+        assert_eq!(axis_threshold_value(0.8, 0.5, 1.0), 1.0);
+    }
+
+    #[test]
+    fn axis_threshold_zero_below_threshold() {
+        assert_eq!(axis_threshold_value(0.3, 0.5, 1.0), 0.0);
+    }
+
+    #[test]
+    fn axis_threshold_zero_when_opposite_direction() {
+        assert_eq!(axis_threshold_value(-0.8, 0.5, 1.0), 0.0);
+    }
+}